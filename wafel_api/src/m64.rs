@@ -328,6 +328,59 @@ pub fn try_save_m64(filename: &str, metadata: &M64Metadata, inputs: &[Input]) ->
     })
 }
 
+/// Records a live playthrough into a standard .m64 file.
+///
+/// Unlike [try_save_m64], which writes a known list of inputs all at once,
+/// `RecordM64` is meant to be driven frame-by-frame while a [Game](crate::Game)
+/// is running: call [Self::record] once per frame with whichever [Input] was
+/// actually applied that frame (whether it came from a loaded movie or from
+/// live controls), then call [Self::try_close] to flush the recording to
+/// disk. The resulting file can be fed straight back into [try_load_m64].
+#[derive(Debug, Clone)]
+pub struct RecordM64 {
+    metadata: M64Metadata,
+    inputs: Vec<Input>,
+}
+
+impl RecordM64 {
+    /// Start a new recording with the given metadata.
+    pub fn new(metadata: M64Metadata) -> Self {
+        Self {
+            metadata,
+            inputs: Vec::new(),
+        }
+    }
+
+    /// Append the input that was applied for the current frame.
+    pub fn record(&mut self, input: Input) {
+        self.inputs.push(input);
+    }
+
+    /// The inputs recorded so far.
+    pub fn inputs(&self) -> &[Input] {
+        &self.inputs
+    }
+
+    /// Flush the recording to a file in the standard .m64 layout.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the file can't be written.
+    #[track_caller]
+    pub fn close(self, filename: &str) {
+        if let Err(error) = self.try_close(filename) {
+            panic!("Error:\n  {}\n", error);
+        }
+    }
+
+    /// Flush the recording to a file in the standard .m64 layout.
+    ///
+    /// Returns an error if the file can't be written.
+    pub fn try_close(self, filename: &str) -> Result<(), Error> {
+        try_save_m64(filename, &self.metadata, &self.inputs)
+    }
+}
+
 fn save_m64_impl(filename: &str, metadata: &M64Metadata, inputs: &[Input]) -> io::Result<()> {
     if let Some(dir) = Path::new(filename).parent() {
         fs::create_dir_all(dir)?;
@@ -43,6 +43,7 @@ pub struct PointElement {
     pub pos: Vec3,
     pub size: f32,
     pub color: Vec4,
+    pub blend_mode: BlendMode,
 }
 
 impl PointElement {
@@ -51,6 +52,7 @@ impl PointElement {
             pos,
             size: 1.0,
             color: [1.0, 1.0, 1.0, 1.0].into(),
+            blend_mode: BlendMode::default(),
         }
     }
 
@@ -64,6 +66,14 @@ impl PointElement {
         self
     }
 
+    /// Sets the blend mode used when this point is transparent (`color[3] < 1.0`).
+    ///
+    /// Ignored for opaque points, which are always drawn with a plain overwrite.
+    pub fn with_blend_mode(mut self, blend_mode: BlendMode) -> Self {
+        self.blend_mode = blend_mode;
+        self
+    }
+
     pub fn bounding_rect(&self) -> Rect3 {
         Rect3::point(self.pos)
     }
@@ -74,6 +84,7 @@ impl PointElement {
 pub struct LineElement {
     pub vertices: [Vec3; 2],
     pub color: Vec4,
+    pub blend_mode: BlendMode,
 }
 
 impl LineElement {
@@ -81,6 +92,7 @@ impl LineElement {
         Self {
             vertices,
             color: [1.0, 1.0, 1.0, 1.0].into(),
+            blend_mode: BlendMode::default(),
         }
     }
 
@@ -89,6 +101,14 @@ impl LineElement {
         self
     }
 
+    /// Sets the blend mode used when this line is transparent (`color[3] < 1.0`).
+    ///
+    /// Ignored for opaque lines, which are always drawn with a plain overwrite.
+    pub fn with_blend_mode(mut self, blend_mode: BlendMode) -> Self {
+        self.blend_mode = blend_mode;
+        self
+    }
+
     pub fn bounding_rect(&self) -> Rect3 {
         Rect3::point(self.vertices[0]).enclose(Rect3::point(self.vertices[1]))
     }
@@ -100,6 +120,7 @@ pub struct TriangleElement {
     pub color: Vec4,
     pub surface_gradient: bool,
     pub transparency_hint: TransparencyHint,
+    pub blend_mode: BlendMode,
 }
 
 impl TriangleElement {
@@ -109,6 +130,7 @@ impl TriangleElement {
             color: [1.0, 1.0, 1.0, 1.0].into(),
             surface_gradient: false,
             transparency_hint: TransparencyHint::None,
+            blend_mode: BlendMode::default(),
         }
     }
 
@@ -131,6 +153,14 @@ impl TriangleElement {
         self
     }
 
+    /// Sets the blend mode used when this triangle is transparent (`color[3] < 1.0`).
+    ///
+    /// Ignored for opaque triangles, which are always drawn with a plain overwrite.
+    pub fn with_blend_mode(mut self, blend_mode: BlendMode) -> Self {
+        self.blend_mode = blend_mode;
+        self
+    }
+
     pub fn bounding_rect(&self) -> Rect3 {
         Rect3::point(self.vertices[0])
             .enclose(Rect3::point(self.vertices[1]))
@@ -154,3 +184,22 @@ pub enum TransparencyHint {
     /// topmost WallHitbox triangle.
     WallHitbox,
 }
+
+/// Selects the blend function used to composite a transparent element onto the
+/// framebuffer, corresponding to the `[source, destination] x [color, alpha]`
+/// factor pairs from the WebGPU blending model.
+///
+/// Only affects elements with alpha `< 1.0`; opaque elements always overwrite
+/// the framebuffer regardless of this setting.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum BlendMode {
+    /// Standard "over" compositing: `src.rgb * src.a + dst.rgb * (1 - src.a)`.
+    #[default]
+    AlphaBlend,
+    /// Adds the element's color onto the destination without attenuating it,
+    /// for glow-style overlays: `src.rgb * src.a + dst.rgb`.
+    Additive,
+    /// Like [Self::AlphaBlend], but assumes `src.rgb` is already multiplied by
+    /// `src.a`: `src.rgb + dst.rgb * (1 - src.a)`.
+    PremultipliedAlpha,
+}
@@ -0,0 +1,150 @@
+use std::rc::Rc;
+
+/// A sparse history of save states that thins itself to a geometric spread of
+/// distances behind the latest recorded frame, so roughly `log2(window)`
+/// checkpoints cover the whole history instead of one per frame.
+///
+/// This gives near-constant-cost seeking to any frame within the window:
+/// [Self::latest_at_or_before] is always within a factor of 2 of the
+/// requested distance, so re-advancing from it takes at most that many
+/// frames.
+///
+/// Generic over the state type `T` (in practice [wafel_api::SaveState]) so
+/// the thinning logic can be unit-tested without a running game.
+#[derive(Debug)]
+pub struct CheckpointLadder<T> {
+    /// Sorted ascending by frame.
+    checkpoints: Vec<(u32, Rc<T>)>,
+    window: u32,
+}
+
+impl<T> CheckpointLadder<T> {
+    /// Creates an empty ladder that forgets checkpoints more than `window`
+    /// frames behind the latest recorded one.
+    pub fn new(window: u32) -> Self {
+        Self {
+            checkpoints: Vec::new(),
+            window,
+        }
+    }
+
+    /// Returns whether a checkpoint taken at `frame` would actually extend the
+    /// ladder, rather than being thinned away again as soon as the next one
+    /// is recorded. Callers should check this *before* paying the cost of a
+    /// save state, since [Self::record] happily accepts (and immediately
+    /// discards) a redundant one.
+    ///
+    /// A new rung is needed once the gap back to the newest checkpoint has
+    /// grown to double the gap before it, which is exactly the spacing
+    /// [Self::rebalance] keeps — so checkpoints land at `frame`, `frame - 1`,
+    /// `frame - 3`, `frame - 7`, ... and only `O(log window)` are ever taken.
+    pub fn wants_checkpoint(&self, frame: u32) -> bool {
+        match self.checkpoints.as_slice() {
+            [] => true,
+            [(newest_frame, _)] => frame > *newest_frame,
+            [.., (second_frame, _), (newest_frame, _)] => {
+                frame > *newest_frame && frame - newest_frame >= (newest_frame - second_frame) * 2
+            }
+        }
+    }
+
+    /// Records a checkpoint at `frame`, then thins older checkpoints so that,
+    /// walking backward from `frame`, the gap to each next-older checkpoint
+    /// is at least double the previous gap.
+    pub fn record(&mut self, frame: u32, state: Rc<T>) {
+        self.checkpoints.push((frame, state));
+        self.rebalance(frame);
+    }
+
+    /// Re-applies the window/geometric-spacing thinning for `frame` without
+    /// recording a new checkpoint. Lets callers that skip a rung (per
+    /// [Self::wants_checkpoint]) still evict entries that have aged out of
+    /// the window.
+    pub fn prune(&mut self, frame: u32) {
+        self.rebalance(frame);
+    }
+
+    /// Returns the latest checkpoint at or before `frame`, if one is still
+    /// within the window.
+    pub fn latest_at_or_before(&self, frame: u32) -> Option<(u32, Rc<T>)> {
+        self.checkpoints
+            .iter()
+            .rev()
+            .find(|&&(checkpoint_frame, _)| checkpoint_frame <= frame)
+            .map(|(checkpoint_frame, state)| (*checkpoint_frame, state.clone()))
+    }
+
+    /// Discards checkpoints recorded after `frame`. Callers seeking backward
+    /// should call this before re-advancing: those checkpoints are ahead of
+    /// the frame the game is about to resume from, and would otherwise stay in
+    /// the ladder (and be compared against by [Self::wants_checkpoint]) until
+    /// the next [Self::record] or [Self::prune] discards them.
+    pub fn drop_after(&mut self, frame: u32) {
+        self.checkpoints
+            .retain(|&(checkpoint_frame, _)| checkpoint_frame <= frame);
+    }
+
+    /// The frames of all checkpoints currently kept, oldest first.
+    pub fn frames(&self) -> impl Iterator<Item = u32> + '_ {
+        self.checkpoints.iter().map(|&(frame, _)| frame)
+    }
+
+    fn rebalance(&mut self, current_frame: u32) {
+        self.checkpoints
+            .retain(|&(frame, _)| frame <= current_frame && current_frame - frame <= self.window);
+
+        let mut kept: Vec<(u32, Rc<T>)> = Vec::new();
+        let mut min_next_distance = 0;
+        for (frame, state) in self.checkpoints.iter().rev() {
+            let distance = current_frame - frame;
+            if distance >= min_next_distance {
+                kept.push((*frame, state.clone()));
+                min_next_distance = distance.saturating_mul(2).max(1);
+            }
+        }
+        kept.reverse();
+        self.checkpoints = kept;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn ladder_with(window: u32, frames: impl IntoIterator<Item = u32>) -> CheckpointLadder<()> {
+        let mut ladder = CheckpointLadder::new(window);
+        for frame in frames {
+            ladder.record(frame, Rc::new(()));
+        }
+        ladder
+    }
+
+    /// Regression test for the overlay scrubber (chunk103-3): dragging it
+    /// backward past rungs recorded during forward play used to underflow
+    /// `rebalance`'s frame subtraction.
+    #[test]
+    fn seeking_backward_past_ahead_rungs_does_not_underflow() {
+        // Mirrors play reaching frame 5000, then rewinding twice to land on
+        // an earlier rung while later rungs (4997, 4999, 5000) are still in
+        // the ladder, and re-advancing from there.
+        let mut ladder = ladder_with(10_000, [4993, 4997, 4999, 5000]);
+
+        ladder.drop_after(4993);
+        ladder.prune(4994);
+        ladder.prune(4998);
+
+        assert_eq!(ladder.frames().max(), Some(4993));
+    }
+
+    #[test]
+    fn rebalance_ignores_checkpoints_ahead_of_current_frame_without_drop_after() {
+        // Even if a caller forgets drop_after, rebalance must not panic or
+        // wrongly evict everything when it sees frames ahead of current.
+        let mut ladder = ladder_with(10_000, [4993, 4997, 4999, 5000]);
+
+        ladder.prune(4994);
+
+        assert!(ladder.frames().all(|frame| frame <= 4994));
+        assert_eq!(ladder.latest_at_or_before(4994), Some((4993, Rc::new(()))));
+    }
+}
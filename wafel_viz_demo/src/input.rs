@@ -0,0 +1,278 @@
+//! A remappable action/binding layer between raw window events and the app's
+//! `update`/`render` logic.
+//!
+//! Actions are named and typed as either a digital [ButtonAction] or a
+//! continuous [AxisAction]. An [InputLayout] binds physical keys, mouse
+//! buttons, and the mouse wheel to actions; [ActionHandler] tracks the raw
+//! input state and resolves it against a layout so that callers only ever
+//! query actions (`handler.button_pressed(QUICK_RENDER)`,
+//! `handler.axis_value(CAMERA_PAN_X)`) instead of winit key codes.
+
+use std::collections::{HashMap, HashSet};
+
+use winit::event::{ElementState, MouseButton, MouseScrollDelta, VirtualKeyCode, WindowEvent};
+
+/// A named digital action, e.g. a button press or held key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ButtonAction(pub &'static str);
+
+/// A named continuous action, e.g. a movement direction or zoom speed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct AxisAction(pub &'static str);
+
+pub const TOGGLE_FRAME_ADVANCE: ButtonAction = ButtonAction("toggle_frame_advance");
+pub const TOGGLE_RECORDING: ButtonAction = ButtonAction("toggle_recording");
+pub const REWIND: ButtonAction = ButtonAction("rewind");
+pub const QUICK_RENDER: ButtonAction = ButtonAction("quick_render");
+pub const PRINT_FRAME: ButtonAction = ButtonAction("print_frame");
+pub const LOCK_CAMERA_IN_GAME: ButtonAction = ButtonAction("lock_camera_in_game");
+pub const LOCK_CAMERA_MARIO: ButtonAction = ButtonAction("lock_camera_mario");
+pub const ENABLE_FLYCAM: ButtonAction = ButtonAction("enable_flycam");
+pub const MOUSE_LOOK: ButtonAction = ButtonAction("mouse_look");
+
+pub const GAME_SPEED: AxisAction = AxisAction("game_speed");
+pub const CAMERA_PAN_X: AxisAction = AxisAction("camera_pan_x");
+pub const CAMERA_PAN_Y: AxisAction = AxisAction("camera_pan_y");
+pub const CAMERA_PAN_Z: AxisAction = AxisAction("camera_pan_z");
+pub const CAMERA_ZOOM: AxisAction = AxisAction("camera_zoom");
+
+/// A physical input bound to a [ButtonAction].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ButtonBinding {
+    Key(VirtualKeyCode),
+    MouseButton(MouseButton),
+}
+
+/// A physical input bound to an [AxisAction].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AxisBinding {
+    /// Contributes a fixed `value` to the axis while `key` is held.
+    Key { key: VirtualKeyCode, value: f32 },
+    /// Two keys driving a single axis in opposite directions: `positive` moves
+    /// towards `1.0`, `negative` towards `-1.0`.
+    KeyPair {
+        positive: VirtualKeyCode,
+        negative: VirtualKeyCode,
+    },
+    /// The vertical mouse-wheel scroll delta accumulated since the last
+    /// [ActionHandler::end_frame] call.
+    MouseWheel,
+}
+
+/// A set of bindings from physical inputs to [ButtonAction]s/[AxisAction]s.
+///
+/// [InputLayout::default] reproduces the demo app's original hardcoded
+/// controls; an alternate layout can be built by starting from
+/// [InputLayout::empty] and binding whichever actions it needs.
+#[derive(Debug, Clone)]
+pub struct InputLayout {
+    buttons: HashMap<ButtonAction, Vec<ButtonBinding>>,
+    axes: HashMap<AxisAction, Vec<AxisBinding>>,
+}
+
+impl InputLayout {
+    /// A layout with no bindings.
+    pub fn empty() -> Self {
+        Self {
+            buttons: HashMap::new(),
+            axes: HashMap::new(),
+        }
+    }
+
+    pub fn bind_button(mut self, action: ButtonAction, binding: ButtonBinding) -> Self {
+        self.buttons.entry(action).or_default().push(binding);
+        self
+    }
+
+    pub fn bind_axis(mut self, action: AxisAction, binding: AxisBinding) -> Self {
+        self.axes.entry(action).or_default().push(binding);
+        self
+    }
+}
+
+impl Default for InputLayout {
+    fn default() -> Self {
+        Self {
+            buttons: HashMap::new(),
+            axes: HashMap::new(),
+        }
+        .bind_button(
+            TOGGLE_FRAME_ADVANCE,
+            ButtonBinding::Key(VirtualKeyCode::Key1),
+        )
+        .bind_button(REWIND, ButtonBinding::Key(VirtualKeyCode::Left))
+        .bind_button(TOGGLE_RECORDING, ButtonBinding::Key(VirtualKeyCode::Key2))
+        .bind_button(QUICK_RENDER, ButtonBinding::Key(VirtualKeyCode::Q))
+        .bind_button(PRINT_FRAME, ButtonBinding::Key(VirtualKeyCode::Return))
+        .bind_button(LOCK_CAMERA_IN_GAME, ButtonBinding::Key(VirtualKeyCode::C))
+        .bind_button(LOCK_CAMERA_MARIO, ButtonBinding::Key(VirtualKeyCode::M))
+        .bind_button(ENABLE_FLYCAM, ButtonBinding::Key(VirtualKeyCode::F))
+        .bind_button(MOUSE_LOOK, ButtonBinding::MouseButton(MouseButton::Right))
+        .bind_axis(
+            GAME_SPEED,
+            AxisBinding::Key {
+                key: VirtualKeyCode::Right,
+                value: 1.0,
+            },
+        )
+        .bind_axis(
+            GAME_SPEED,
+            AxisBinding::Key {
+                key: VirtualKeyCode::Down,
+                value: 10.0,
+            },
+        )
+        .bind_axis(
+            GAME_SPEED,
+            AxisBinding::Key {
+                key: VirtualKeyCode::Up,
+                value: 100.0,
+            },
+        )
+        .bind_axis(
+            CAMERA_PAN_X,
+            AxisBinding::KeyPair {
+                positive: VirtualKeyCode::S,
+                negative: VirtualKeyCode::A,
+            },
+        )
+        .bind_axis(
+            CAMERA_PAN_Y,
+            AxisBinding::KeyPair {
+                positive: VirtualKeyCode::Space,
+                negative: VirtualKeyCode::LShift,
+            },
+        )
+        .bind_axis(
+            CAMERA_PAN_Z,
+            AxisBinding::KeyPair {
+                positive: VirtualKeyCode::R,
+                negative: VirtualKeyCode::W,
+            },
+        )
+        .bind_axis(CAMERA_ZOOM, AxisBinding::MouseWheel)
+    }
+}
+
+/// Tracks raw keyboard/mouse state and resolves it against an [InputLayout] so
+/// that app code only deals with named actions.
+#[derive(Debug)]
+pub struct ActionHandler {
+    layout: InputLayout,
+    held_keys: HashSet<VirtualKeyCode>,
+    held_mouse_buttons: HashSet<MouseButton>,
+    just_pressed_keys: HashSet<VirtualKeyCode>,
+    just_pressed_mouse_buttons: HashSet<MouseButton>,
+    wheel_delta: f32,
+}
+
+impl ActionHandler {
+    pub fn new(layout: InputLayout) -> Self {
+        Self {
+            layout,
+            held_keys: HashSet::new(),
+            held_mouse_buttons: HashSet::new(),
+            just_pressed_keys: HashSet::new(),
+            just_pressed_mouse_buttons: HashSet::new(),
+            wheel_delta: 0.0,
+        }
+    }
+
+    /// Feeds a raw window event into the handler. Should be called from
+    /// `App::window_event` before querying any actions for the frame.
+    pub fn window_event(&mut self, event: &WindowEvent) {
+        match event {
+            WindowEvent::KeyboardInput { input, .. } => {
+                if let Some(key) = input.virtual_keycode {
+                    match input.state {
+                        ElementState::Pressed => {
+                            if self.held_keys.insert(key) {
+                                self.just_pressed_keys.insert(key);
+                            }
+                        }
+                        ElementState::Released => {
+                            self.held_keys.remove(&key);
+                        }
+                    }
+                }
+            }
+            WindowEvent::MouseInput { state, button, .. } => match state {
+                ElementState::Pressed => {
+                    if self.held_mouse_buttons.insert(*button) {
+                        self.just_pressed_mouse_buttons.insert(*button);
+                    }
+                }
+                ElementState::Released => {
+                    self.held_mouse_buttons.remove(button);
+                }
+            },
+            WindowEvent::MouseWheel { delta, .. } => {
+                self.wheel_delta += match *delta {
+                    MouseScrollDelta::LineDelta(_, dy) => dy,
+                    MouseScrollDelta::PixelDelta(d) => (d.y / 30.0) as f32,
+                };
+            }
+            _ => {}
+        }
+    }
+
+    /// Clears the edge-triggered state (just-pressed buttons, wheel delta)
+    /// accumulated since the last call. Should be called once per frame after
+    /// `button_just_pressed`/`axis_value` have been queried for the frame.
+    pub fn end_frame(&mut self) {
+        self.just_pressed_keys.clear();
+        self.just_pressed_mouse_buttons.clear();
+        self.wheel_delta = 0.0;
+    }
+
+    /// Returns whether any binding for `action` is currently held.
+    pub fn button_pressed(&self, action: ButtonAction) -> bool {
+        self.bindings_for(action).any(|binding| match binding {
+            ButtonBinding::Key(key) => self.held_keys.contains(key),
+            ButtonBinding::MouseButton(button) => self.held_mouse_buttons.contains(button),
+        })
+    }
+
+    /// Returns whether any binding for `action` transitioned from released to
+    /// pressed since the last [Self::end_frame].
+    pub fn button_just_pressed(&self, action: ButtonAction) -> bool {
+        self.bindings_for(action).any(|binding| match binding {
+            ButtonBinding::Key(key) => self.just_pressed_keys.contains(key),
+            ButtonBinding::MouseButton(button) => self.just_pressed_mouse_buttons.contains(button),
+        })
+    }
+
+    /// Returns the sum of all binding contributions for `action`.
+    pub fn axis_value(&self, action: AxisAction) -> f32 {
+        self.layout
+            .axes
+            .get(&action)
+            .into_iter()
+            .flatten()
+            .map(|binding| match binding {
+                AxisBinding::Key { key, value } => {
+                    if self.held_keys.contains(key) {
+                        *value
+                    } else {
+                        0.0
+                    }
+                }
+                AxisBinding::KeyPair { positive, negative } => {
+                    let mut value = 0.0;
+                    if self.held_keys.contains(positive) {
+                        value += 1.0;
+                    }
+                    if self.held_keys.contains(negative) {
+                        value -= 1.0;
+                    }
+                    value
+                }
+                AxisBinding::MouseWheel => self.wheel_delta,
+            })
+            .sum()
+    }
+
+    fn bindings_for(&self, action: ButtonAction) -> impl Iterator<Item = &ButtonBinding> {
+        self.layout.buttons.get(&action).into_iter().flatten()
+    }
+}
@@ -1,13 +1,12 @@
 #![allow(clippy::needless_update)]
 
-use std::{
-    collections::{HashMap, HashSet},
-    rc::Rc,
-    time::{Duration, Instant},
-};
+use std::{rc::Rc, time::{Duration, Instant}};
 
+use checkpoint_ladder::CheckpointLadder;
+use input::{ActionHandler, InputLayout};
+use overlay::DebugOverlay;
 use remote_dll::RemoteDllApp;
-use wafel_api::{try_load_m64, Error, Game, Input, SaveState};
+use wafel_api::{try_load_m64, Error, Game, Input, M64Metadata, RecordM64, SaveState, SM64Version};
 use wafel_memory::GameMemory;
 use wafel_viz_sm64::{
     viz_render, Camera, Element, InGameRenderMode, Line, ObjectCull, OrthoCamera,
@@ -15,8 +14,11 @@ use wafel_viz_sm64::{
 };
 use wafel_viz_wgpu::VizRenderer;
 use window::{open_window_and_run, App};
-use winit::event::{ElementState, MouseButton, MouseScrollDelta, VirtualKeyCode, WindowEvent};
+use winit::event::{ElementState, MouseButton, WindowEvent};
 
+mod checkpoint_ladder;
+mod input;
+mod overlay;
 mod remote_dll;
 mod window;
 
@@ -29,40 +31,81 @@ fn main() {
 struct VizApp {
     game: Game,
     inputs: Vec<Input>,
-    save_states: HashMap<u32, Rc<SaveState>>,
+    checkpoints: CheckpointLadder<SaveState>,
     camera_control: PerspCameraControl,
-    held_keys: HashSet<VirtualKeyCode>,
+    actions: ActionHandler,
+    auto_advance: bool,
+    current_input: Input,
+    recording: Option<RecordM64>,
     viz_renderer: VizRenderer,
     last_update: Instant,
     time_since_game_advance: Duration,
+    overlay: DebugOverlay,
+    in_game_render_mode: InGameRenderMode,
+    surface_mode: SurfaceMode,
+    object_cull: ObjectCull,
+    wall_hitbox_radius: f32,
+    show_camera_focus: bool,
+    last_scale_factor: f32,
+    last_frame_instant: Instant,
 }
 
-const SAVE_STATE_FREQ: u32 = 1000;
-const SAVE_STATE_DUR: u32 = 10_000;
+/// How many frames of history [CheckpointLadder] keeps behind the current frame.
+const CHECKPOINT_WINDOW: u32 = 10_000;
 
 impl VizApp {
-    fn frame_advance(&mut self) -> Result<(), Error> {
+    /// Advances the game by one frame and refreshes the checkpoint ladder.
+    /// Shared by genuine forward playback ([Self::frame_advance]) and the
+    /// re-advance loop in [Self::seek_to]; only the former also feeds an
+    /// active recording, so that rewinding/scrubbing doesn't append frames
+    /// the recording already has.
+    fn advance_game_frame(&mut self) -> Result<(), Error> {
         if let Some(&input) = self.inputs.get(self.game.frame() as usize) {
             self.game.try_set_input(input)?;
+            self.current_input = input;
         }
-        if self.held_keys.contains(&VirtualKeyCode::Q) {
+        if self.actions.button_pressed(input::QUICK_RENDER) {
             self.game.write("gQuickRender", 1.into());
         }
         self.game.advance();
 
-        if self.game.frame() % SAVE_STATE_FREQ == 0 {
-            self.save_states
-                .insert(self.game.frame(), Rc::new(self.game.save_state()));
-            self.save_states = self
-                .save_states
-                .clone()
-                .into_iter()
-                .filter(|e| e.0 + SAVE_STATE_DUR >= self.game.frame())
-                .collect();
+        let frame = self.game.frame();
+        if self.checkpoints.wants_checkpoint(frame) {
+            self.checkpoints.record(frame, Rc::new(self.game.save_state()));
+        } else {
+            self.checkpoints.prune(frame);
         }
 
         Ok(())
     }
+
+    fn frame_advance(&mut self) -> Result<(), Error> {
+        self.advance_game_frame()?;
+        if let Some(recording) = &mut self.recording {
+            recording.record(self.current_input);
+        }
+        Ok(())
+    }
+
+    /// Seeks to `frame` by loading the latest checkpoint at or before it and
+    /// re-advancing to catch up. Shared by the Left-arrow rewind and the
+    /// overlay's scrubber, so both get the ladder's near-constant-cost seek.
+    /// Re-advanced frames are not appended to an active recording, whether
+    /// the seek goes backward or forward.
+    fn seek_to(&mut self, frame: u32) -> Result<(), Error> {
+        if let Some((checkpoint_frame, state)) = self.checkpoints.latest_at_or_before(frame) {
+            self.game.try_load_state(&state)?;
+            // Loading an earlier checkpoint leaves any rungs ahead of it
+            // (from before the seek) still in the ladder; drop them so
+            // re-advancing below never rebalances against a current frame
+            // older than a stored checkpoint.
+            self.checkpoints.drop_after(checkpoint_frame);
+        }
+        while self.game.frame() < frame {
+            self.advance_game_frame()?;
+        }
+        Ok(())
+    }
 }
 
 impl App for VizApp {
@@ -73,12 +116,27 @@ impl App for VizApp {
         let mut app = VizApp {
             game,
             inputs,
-            save_states: HashMap::new(),
+            checkpoints: CheckpointLadder::new(CHECKPOINT_WINDOW),
             camera_control: PerspCameraControl::new(),
-            held_keys: HashSet::new(),
+            actions: ActionHandler::new(InputLayout::default()),
+            auto_advance: false,
+            current_input: Input {
+                buttons: 0,
+                stick_x: 0,
+                stick_y: 0,
+            },
+            recording: None,
             viz_renderer: VizRenderer::new(device, output_format, 1),
             last_update: Instant::now(),
             time_since_game_advance: Duration::ZERO,
+            overlay: DebugOverlay::new(device, output_format),
+            in_game_render_mode: InGameRenderMode::Rerender,
+            surface_mode: SurfaceMode::Physical,
+            object_cull: ObjectCull::ShowAll,
+            wall_hitbox_radius: 50.0,
+            show_camera_focus: true,
+            last_scale_factor: 1.0,
+            last_frame_instant: Instant::now(),
         };
 
         // bitfs: 41884
@@ -94,65 +152,21 @@ impl App for VizApp {
     }
 
     fn window_event(&mut self, event: &winit::event::WindowEvent) -> Result<(), Error> {
+        self.actions.window_event(event);
+        self.overlay.window_event(event, self.last_scale_factor);
+
         match event {
-            WindowEvent::MouseInput { state, button, .. } => match (button, state) {
-                (MouseButton::Left, ElementState::Pressed) => {
-                    self.camera_control.press_mouse_left()
-                }
-                (MouseButton::Left, ElementState::Released) => {
-                    self.camera_control.release_mouse_left()
-                }
-                _ => {}
+            WindowEvent::MouseInput {
+                state,
+                button: MouseButton::Left,
+                ..
+            } => match state {
+                ElementState::Pressed => self.camera_control.press_mouse_left(),
+                ElementState::Released => self.camera_control.release_mouse_left(),
             },
             WindowEvent::CursorMoved { position, .. } => self
                 .camera_control
                 .move_mouse([position.x as f32, position.y as f32]),
-            WindowEvent::MouseWheel { delta, .. } => {
-                let amount = match *delta {
-                    MouseScrollDelta::LineDelta(_, dy) => dy,
-                    MouseScrollDelta::PixelDelta(d) => (d.y / 30.0) as f32,
-                };
-                self.camera_control.scroll_wheel(amount);
-            }
-            WindowEvent::KeyboardInput { input, .. } => {
-                if let Some(key) = input.virtual_keycode {
-                    match input.state {
-                        ElementState::Pressed => {
-                            if key == VirtualKeyCode::Return {
-                                eprintln!("frame = {}", self.game.frame());
-                            }
-                            if key == VirtualKeyCode::Key1 {
-                                if self.held_keys.contains(&VirtualKeyCode::Right) {
-                                    self.held_keys.remove(&VirtualKeyCode::Right);
-                                } else {
-                                    self.held_keys.insert(VirtualKeyCode::Right);
-                                }
-                            }
-                            if key == VirtualKeyCode::Left {
-                                let frame = self.game.frame().saturating_sub(1) / SAVE_STATE_FREQ
-                                    * SAVE_STATE_FREQ;
-                                if let Some(state) = self.save_states.get(&frame) {
-                                    self.game.try_load_state(state)?;
-                                }
-                            }
-                            if key == VirtualKeyCode::C
-                                && !self.held_keys.contains(&VirtualKeyCode::C)
-                            {
-                                self.camera_control.lock_to_in_game_camera();
-                            }
-                            if key == VirtualKeyCode::M
-                                && !self.held_keys.contains(&VirtualKeyCode::M)
-                            {
-                                self.camera_control.lock_to_mario();
-                            }
-                            self.held_keys.insert(key);
-                        }
-                        ElementState::Released => {
-                            self.held_keys.remove(&key);
-                        }
-                    }
-                }
-            }
             _ => {}
         }
 
@@ -160,54 +174,82 @@ impl App for VizApp {
     }
 
     fn update(&mut self) -> Result<(), Error> {
+        if self.actions.button_just_pressed(input::PRINT_FRAME) {
+            eprintln!("frame = {}", self.game.frame());
+        }
+        if self.actions.button_just_pressed(input::TOGGLE_FRAME_ADVANCE) {
+            self.auto_advance = !self.auto_advance;
+        }
+        if self.actions.button_just_pressed(input::REWIND) {
+            if let Some((frame, _)) = self
+                .checkpoints
+                .latest_at_or_before(self.game.frame().saturating_sub(1))
+            {
+                self.seek_to(frame)?;
+            }
+        }
+        if self.actions.button_just_pressed(input::LOCK_CAMERA_IN_GAME) {
+            self.camera_control.lock_to_in_game_camera();
+        }
+        if self.actions.button_just_pressed(input::LOCK_CAMERA_MARIO) {
+            self.camera_control.lock_to_mario();
+        }
+        if self.actions.button_just_pressed(input::ENABLE_FLYCAM) {
+            self.camera_control.enable_flycam();
+        }
+        self.camera_control
+            .set_mouse_look(self.actions.button_pressed(input::MOUSE_LOOK));
+        if self.actions.button_just_pressed(input::TOGGLE_RECORDING) {
+            match self.recording.take() {
+                Some(recording) => {
+                    let filename = format!("recording_{}.m64", self.game.frame());
+                    match recording.try_close(&filename) {
+                        Ok(()) => eprintln!("saved recording to {}", filename),
+                        Err(error) => eprintln!("failed to save recording: {}", error),
+                    }
+                }
+                None => {
+                    eprintln!("recording started at frame {}", self.game.frame());
+                    self.recording = Some(RecordM64::new(M64Metadata::with_version(
+                        SM64Version::US,
+                    )));
+                }
+            }
+        }
+
         self.time_since_game_advance += self.last_update.elapsed();
         self.last_update = Instant::now();
 
-        let speed = if self.held_keys.contains(&VirtualKeyCode::Right) {
-            1
-        } else if self.held_keys.contains(&VirtualKeyCode::Down) {
-            10
-        } else if self.held_keys.contains(&VirtualKeyCode::Up) {
-            100
-        } else {
-            0
-        };
+        let mut speed = self.actions.axis_value(input::GAME_SPEED);
+        if self.auto_advance {
+            speed += 1.0;
+        }
 
-        if speed == 0 {
+        if speed <= 0.0 {
             self.time_since_game_advance = Duration::ZERO;
         } else {
-            let dt = Duration::from_secs_f32(1.0 / 30.0) / speed;
+            let dt = Duration::from_secs_f32(1.0 / 30.0).div_f32(speed);
             while self.time_since_game_advance >= dt {
                 self.time_since_game_advance -= dt;
                 self.frame_advance()?;
             }
         }
 
-        let mut camera_move = [0.0, 0.0, 0.0];
-        if self.held_keys.contains(&VirtualKeyCode::S) {
-            camera_move[0] += 1.0;
-        }
-        if self.held_keys.contains(&VirtualKeyCode::A) {
-            camera_move[0] -= 1.0;
-        }
-        if self.held_keys.contains(&VirtualKeyCode::Space) {
-            camera_move[1] += 1.0;
-        }
-        if self.held_keys.contains(&VirtualKeyCode::LShift) {
-            camera_move[1] -= 1.0;
-        }
-        if self.held_keys.contains(&VirtualKeyCode::R) {
-            camera_move[2] += 1.0;
-        }
-        if self.held_keys.contains(&VirtualKeyCode::W) {
-            camera_move[2] -= 1.0;
-        }
+        self.camera_control.scroll_wheel(self.actions.axis_value(input::CAMERA_ZOOM));
+
+        let camera_move = [
+            self.actions.axis_value(input::CAMERA_PAN_X),
+            self.actions.axis_value(input::CAMERA_PAN_Y),
+            self.actions.axis_value(input::CAMERA_PAN_Z),
+        ];
         self.camera_control.update(
             &self.game.layout,
             &self.game.memory.with_slot(&self.game.base_slot),
             camera_move,
         )?;
 
+        self.actions.end_frame();
+
         Ok(())
     }
 
@@ -220,6 +262,11 @@ impl App for VizApp {
         output_size: [u32; 2],
         scale_factor: f32,
     ) -> Result<(), Error> {
+        self.last_scale_factor = scale_factor;
+        let now = Instant::now();
+        let fps = 1.0 / now.duration_since(self.last_frame_instant).as_secs_f32().max(1e-6);
+        self.last_frame_instant = now;
+
         let camera = self.camera_control.camera();
 
         // let mario_pos = self.game.try_read("gMarioState.pos")?.try_as_f32_3()?;
@@ -235,18 +282,12 @@ impl App for VizApp {
                 (output_size[0] as f32 / scale_factor) as u32,
                 (output_size[1] as f32 / scale_factor) as u32,
             ],
-            in_game_render_mode: if self.held_keys.contains(&VirtualKeyCode::X) {
-                InGameRenderMode::DisplayList
-            } else if self.held_keys.contains(&VirtualKeyCode::Z) {
-                InGameRenderMode::Disabled
-            } else {
-                InGameRenderMode::Rerender
-            },
+            in_game_render_mode: self.in_game_render_mode,
             camera,
-            show_camera_focus: true,
-            object_cull: ObjectCull::ShowAll,
-            surface_mode: SurfaceMode::Physical,
-            wall_hitbox_radius: 50.0,
+            show_camera_focus: self.show_camera_focus,
+            object_cull: self.object_cull,
+            surface_mode: self.surface_mode,
+            wall_hitbox_radius: self.wall_hitbox_radius,
             // transparent_surfaces: (0..7000).collect(),
             ..Default::default()
         };
@@ -335,7 +376,97 @@ impl App for VizApp {
             self.viz_renderer.render(&mut rp);
         }
 
+        let mut in_game_render_mode = self.in_game_render_mode;
+        let mut surface_mode = self.surface_mode;
+        let mut object_cull = self.object_cull;
+        let mut wall_hitbox_radius = self.wall_hitbox_radius;
+        let mut show_camera_focus = self.show_camera_focus;
+        let frame = self.game.frame();
+        let movie_len = self.inputs.len() as u32;
+        let save_state_frames: Vec<u32> = self.checkpoints.frames().collect();
+
+        let seek_target = self.overlay.render(
+            device,
+            queue,
+            &mut encoder,
+            output_view,
+            output_size,
+            scale_factor,
+            |ctx| {
+                let mut seek_target = None;
+                egui::Window::new("Wafel Viz Debug").show(ctx, |ui| {
+                    ui.label(format!("frame = {}", frame));
+                    ui.label(format!("fps = {:.1}", fps));
+                    ui.label(format!("movie length = {}", movie_len));
+                    ui.label(format!("save states = {:?}", save_state_frames));
+
+                    egui::ComboBox::from_label("in-game render mode")
+                        .selected_text(format!("{:?}", in_game_render_mode))
+                        .show_ui(ui, |ui| {
+                            for mode in [
+                                InGameRenderMode::Rerender,
+                                InGameRenderMode::DisplayList,
+                                InGameRenderMode::Disabled,
+                            ] {
+                                ui.selectable_value(
+                                    &mut in_game_render_mode,
+                                    mode,
+                                    format!("{:?}", mode),
+                                );
+                            }
+                        });
+
+                    egui::ComboBox::from_label("surface mode")
+                        .selected_text(format!("{:?}", surface_mode))
+                        .show_ui(ui, |ui| {
+                            for mode in
+                                [SurfaceMode::Visual, SurfaceMode::Physical, SurfaceMode::None]
+                            {
+                                ui.selectable_value(&mut surface_mode, mode, format!("{:?}", mode));
+                            }
+                        });
+
+                    egui::ComboBox::from_label("object cull")
+                        .selected_text(format!("{:?}", object_cull))
+                        .show_ui(ui, |ui| {
+                            for cull in [ObjectCull::Normal, ObjectCull::ShowAll] {
+                                ui.selectable_value(&mut object_cull, cull, format!("{:?}", cull));
+                            }
+                        });
+
+                    ui.add(
+                        egui::Slider::new(&mut wall_hitbox_radius, 0.0..=500.0)
+                            .text("wall hitbox radius"),
+                    );
+                    ui.checkbox(&mut show_camera_focus, "show camera focus");
+
+                    if movie_len > 0 {
+                        let mut scrubber_frame = frame.min(movie_len.saturating_sub(1));
+                        let response = ui.add(
+                            egui::Slider::new(&mut scrubber_frame, 0..=movie_len.saturating_sub(1))
+                                .text("frame"),
+                        );
+                        if response.dragged() {
+                            seek_target = Some(scrubber_frame);
+                        }
+                    }
+                });
+                seek_target
+            },
+        );
+
+        self.in_game_render_mode = in_game_render_mode;
+        self.surface_mode = surface_mode;
+        self.object_cull = object_cull;
+        self.wall_hitbox_radius = wall_hitbox_radius;
+        self.show_camera_focus = show_camera_focus;
+
         queue.submit([encoder.finish()]);
+
+        if let Some(target_frame) = seek_target {
+            self.seek_to(target_frame)?;
+        }
+
         Ok(())
     }
 }
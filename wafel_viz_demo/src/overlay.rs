@@ -0,0 +1,153 @@
+//! A minimal egui debug overlay painted directly into the wgpu output.
+//!
+//! `App::window_event`/`App::render` don't carry a `winit::window::Window`
+//! reference (see [window](crate::window)), so instead of pulling in
+//! `egui-winit` this translates the handful of [WindowEvent]s the overlay's
+//! widgets actually need (pointer position/buttons, scroll) by hand.
+
+use egui::{ClippedPrimitive, Context, Event, FullOutput, Modifiers, PointerButton, RawInput};
+use egui_wgpu::{Renderer, ScreenDescriptor};
+use winit::event::{ElementState, MouseButton, MouseScrollDelta, WindowEvent};
+
+/// Paints an egui UI over the current frame's wgpu output.
+pub struct DebugOverlay {
+    ctx: Context,
+    renderer: Renderer,
+    raw_input: RawInput,
+    pointer_pos: egui::Pos2,
+}
+
+impl std::fmt::Debug for DebugOverlay {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DebugOverlay").finish_non_exhaustive()
+    }
+}
+
+impl DebugOverlay {
+    pub fn new(device: &wgpu::Device, output_format: wgpu::TextureFormat) -> Self {
+        Self {
+            ctx: Context::default(),
+            renderer: Renderer::new(device, output_format, None, 1),
+            raw_input: RawInput::default(),
+            pointer_pos: egui::Pos2::ZERO,
+        }
+    }
+
+    /// Feeds a raw window event into the overlay. Should be called from
+    /// `App::window_event` before the next [Self::render].
+    ///
+    /// `scale_factor` should be the same value passed to `App::render`, since
+    /// `window_event` doesn't otherwise have access to it.
+    pub fn window_event(&mut self, event: &WindowEvent, scale_factor: f32) {
+        match event {
+            WindowEvent::CursorMoved { position, .. } => {
+                self.pointer_pos = egui::pos2(
+                    position.x as f32 / scale_factor,
+                    position.y as f32 / scale_factor,
+                );
+                self.raw_input
+                    .events
+                    .push(Event::PointerMoved(self.pointer_pos));
+            }
+            WindowEvent::MouseInput { state, button, .. } => {
+                if let Some(button) = translate_mouse_button(*button) {
+                    self.raw_input.events.push(Event::PointerButton {
+                        pos: self.pointer_pos,
+                        button,
+                        pressed: *state == ElementState::Pressed,
+                        modifiers: Modifiers::default(),
+                    });
+                }
+            }
+            WindowEvent::MouseWheel { delta, .. } => {
+                let delta = match *delta {
+                    MouseScrollDelta::LineDelta(x, y) => egui::vec2(x, y) * 20.0,
+                    MouseScrollDelta::PixelDelta(d) => egui::vec2(d.x as f32, d.y as f32),
+                };
+                self.raw_input.events.push(Event::MouseWheel {
+                    unit: egui::MouseWheelUnit::Point,
+                    delta,
+                    modifiers: Modifiers::default(),
+                });
+            }
+            _ => {}
+        }
+    }
+
+    /// Runs `build_ui` against this frame's egui context and paints the
+    /// result into `view`. Returns whatever `build_ui` returns, so callers
+    /// can thread out e.g. a scrubber seek request.
+    #[allow(clippy::too_many_arguments)]
+    pub fn render<R>(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        view: &wgpu::TextureView,
+        output_size: [u32; 2],
+        scale_factor: f32,
+        build_ui: impl FnOnce(&Context) -> R,
+    ) -> R {
+        self.raw_input.screen_rect = Some(egui::Rect::from_min_size(
+            egui::Pos2::ZERO,
+            egui::vec2(
+                output_size[0] as f32 / scale_factor,
+                output_size[1] as f32 / scale_factor,
+            ),
+        ));
+        self.raw_input.pixels_per_point = Some(scale_factor);
+
+        let raw_input = std::mem::take(&mut self.raw_input);
+        let mut result = None;
+        let FullOutput {
+            textures_delta,
+            shapes,
+            pixels_per_point,
+            ..
+        } = self.ctx.run(raw_input, |ctx| result = Some(build_ui(ctx)));
+        let result = result.expect("build_ui is always called by Context::run");
+
+        let paint_jobs: Vec<ClippedPrimitive> = self.ctx.tessellate(shapes, pixels_per_point);
+        let screen_descriptor = ScreenDescriptor {
+            size_in_pixels: output_size,
+            pixels_per_point,
+        };
+
+        for (id, delta) in &textures_delta.set {
+            self.renderer.update_texture(device, queue, *id, delta);
+        }
+        self.renderer
+            .update_buffers(device, queue, encoder, &paint_jobs, &screen_descriptor);
+
+        {
+            let mut rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("egui overlay"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: true,
+                    },
+                })],
+                depth_stencil_attachment: None,
+            });
+            self.renderer.render(&mut rpass, &paint_jobs, &screen_descriptor);
+        }
+
+        for id in &textures_delta.free {
+            self.renderer.free_texture(id);
+        }
+
+        result
+    }
+}
+
+fn translate_mouse_button(button: MouseButton) -> Option<PointerButton> {
+    match button {
+        MouseButton::Left => Some(PointerButton::Primary),
+        MouseButton::Right => Some(PointerButton::Secondary),
+        MouseButton::Middle => Some(PointerButton::Middle),
+        _ => None,
+    }
+}
@@ -0,0 +1,361 @@
+use std::{num::Wrapping, time::Instant};
+
+use fast3d::util::{atan2s, coss, sins, Matrixf};
+use wafel_data_access::{DataReadable, MemoryLayout};
+use wafel_data_type::Angle;
+use wafel_memory::MemoryRead;
+
+use crate::{error::VizError, Camera, LookAtCamera};
+
+/// Units per second a held WASD/Space/Shift key moves the flycam.
+const DEFAULT_FLYCAM_SPEED: f32 = 1000.0;
+/// Angle units the flycam turns per pixel of mouse-look motion.
+const DEFAULT_FLYCAM_TURN_SPEED: i32 = 8;
+
+/// Drives a free-roaming perspective [Camera] from mouse drags/scroll and, in
+/// flycam mode, WASD/Space/Shift movement and mouse-look.
+///
+/// Defaults to following the in-game camera; [Self::lock_to_mario] and
+/// [Self::enable_flycam] switch to the other presets, and
+/// [Self::lock_to_in_game_camera] snaps back.
+#[derive(Debug, Clone, Default)]
+pub struct PerspCameraControl {
+    camera: Camera,
+    mouse_pos: Option<[f32; 2]>,
+    in_game_camera: Option<InGameCamera>,
+    mario_pos: Option<[f32; 3]>,
+    camera_override: Option<CameraOverride>,
+    drag_start: Option<DragStart>,
+    flycam: Option<FlycamState>,
+    mouse_look: bool,
+    speed: f32,
+    turn_speed: i32,
+    prev_update: Option<Instant>,
+}
+
+#[derive(Debug, Clone)]
+struct DragStart {
+    mouse_pos: [f32; 2],
+    angle: [Angle; 3],
+}
+
+#[derive(Debug, Clone)]
+struct CameraOverride {
+    angle: [Angle; 3],
+    dist: f32,
+    focus: Focus,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Focus {
+    InGame,
+    Mario,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct FlycamState {
+    pos: [f32; 3],
+    yaw: Angle,
+    pitch: Angle,
+}
+
+#[derive(Debug, Clone, DataReadable)]
+#[struct_name("LakituState")]
+struct InGameCamera {
+    pos: [f32; 3],
+    focus: [f32; 3],
+    roll: Angle,
+}
+
+impl InGameCamera {
+    fn dfocus(&self) -> [f32; 3] {
+        [
+            self.focus[0] - self.pos[0],
+            self.focus[1] - self.pos[1],
+            self.focus[2] - self.pos[2],
+        ]
+    }
+
+    fn dist(&self) -> f32 {
+        let [dx, dy, dz] = self.dfocus();
+        (dx * dx + dy * dy + dz * dz).sqrt()
+    }
+
+    fn pitch(&self) -> Angle {
+        let [dx, dy, dz] = self.dfocus();
+        let xz = (dx * dx + dz * dz).sqrt();
+        atan2s(xz, dy)
+    }
+
+    fn yaw(&self) -> Angle {
+        let [dx, _, dz] = self.dfocus();
+        atan2s(dz, dx)
+    }
+
+    fn angle(&self) -> [Angle; 3] {
+        [self.pitch(), self.yaw(), self.roll]
+    }
+}
+
+impl PerspCameraControl {
+    pub fn new() -> Self {
+        Self {
+            speed: DEFAULT_FLYCAM_SPEED,
+            turn_speed: DEFAULT_FLYCAM_TURN_SPEED,
+            ..Default::default()
+        }
+    }
+
+    /// The camera produced by the most recent [Self::update].
+    pub fn camera(&self) -> Camera {
+        self.camera
+    }
+
+    pub fn move_mouse(&mut self, pos: [f32; 2]) {
+        if let (true, Some(flycam), Some(prev_pos)) =
+            (self.mouse_look, &mut self.flycam, self.mouse_pos)
+        {
+            let delta = [pos[0] - prev_pos[0], pos[1] - prev_pos[1]];
+            flycam.yaw -= Wrapping((delta[0] * self.turn_speed as f32) as i32 as i16);
+            flycam.pitch = (flycam.pitch - Wrapping((delta[1] * self.turn_speed as f32) as i32 as i16))
+                .clamp(Wrapping(-0x3FF0), Wrapping(0x3FF0));
+        }
+        self.mouse_pos = Some(pos);
+    }
+
+    /// Enables or disables mouse-look: while active and in flycam mode, mouse
+    /// motion steers pan/tilt instead of needing a drag.
+    pub fn set_mouse_look(&mut self, mouse_look: bool) {
+        self.mouse_look = mouse_look;
+    }
+
+    /// Units per second a held WASD/Space/Shift key moves the flycam.
+    pub fn set_speed(&mut self, speed: f32) {
+        self.speed = speed;
+    }
+
+    /// Angle units the flycam turns per pixel of mouse-look motion.
+    pub fn set_turn_speed(&mut self, turn_speed: i32) {
+        self.turn_speed = turn_speed;
+    }
+
+    fn current_angle(&self) -> Option<[Angle; 3]> {
+        self.camera_override
+            .as_ref()
+            .map(|c| c.angle)
+            .or_else(|| self.in_game_camera.as_ref().map(|c| c.angle()))
+    }
+
+    fn current_dist(&self) -> Option<f32> {
+        self.camera_override
+            .as_ref()
+            .map(|c| c.dist)
+            .or_else(|| self.in_game_camera.as_ref().map(|c| c.dist()))
+    }
+
+    fn current_pos(&self) -> Option<[f32; 3]> {
+        if let Some(flycam) = &self.flycam {
+            return Some(flycam.pos);
+        }
+        let Some(camera_override) = self.camera_override.as_ref() else {
+            return self.in_game_camera.as_ref().map(|c| c.pos);
+        };
+        let focus = match camera_override.focus {
+            Focus::InGame => self.in_game_camera.as_ref().map(|c| c.focus),
+            Focus::Mario => self.mario_pos,
+        }?;
+        let [pitch, yaw, _] = camera_override.angle;
+        let r = camera_override.dist;
+        let xz = r * coss(pitch);
+        Some([
+            focus[0] - xz * sins(yaw),
+            focus[1] - r * sins(pitch),
+            focus[2] - xz * coss(yaw),
+        ])
+    }
+
+    fn get_or_init_override(&mut self) -> Option<&mut CameraOverride> {
+        if let Some(in_game_camera) = &self.in_game_camera {
+            Some(self.camera_override.get_or_insert_with(|| CameraOverride {
+                angle: in_game_camera.angle(),
+                dist: in_game_camera.dist(),
+                focus: Focus::InGame,
+            }))
+        } else {
+            None
+        }
+    }
+
+    pub fn press_mouse_left(&mut self) {
+        if self.flycam.is_none() && self.drag_start.is_none() {
+            if let (Some(mouse_pos), Some(angle)) = (self.mouse_pos, self.current_angle()) {
+                self.drag_start = Some(DragStart { mouse_pos, angle });
+            }
+        }
+    }
+
+    pub fn release_mouse_left(&mut self) {
+        self.drag_start = None;
+    }
+
+    pub fn scroll_wheel(&mut self, amount: f32) {
+        if self.flycam.is_some() {
+            return;
+        }
+        if let Some(mut dist) = self.current_dist() {
+            if dist > 0.001 {
+                let mut zoom = (dist / 1500.0).log(0.5);
+                zoom += amount / 5.0;
+                zoom = zoom.clamp(-5.0, 7.0);
+                dist = 0.5f32.powf(zoom) * 1500.0;
+
+                if let Some(camera_override) = self.get_or_init_override() {
+                    camera_override.dist = dist;
+                }
+            }
+        }
+    }
+
+    pub fn lock_to_in_game_camera(&mut self) {
+        self.drag_start = None;
+        self.camera_override = None;
+        self.flycam = None;
+    }
+
+    pub fn lock_to_mario(&mut self) {
+        self.flycam = None;
+        if let (Some(angle), Some(dist)) = (self.current_angle(), self.current_dist()) {
+            self.camera_override = Some(CameraOverride {
+                angle,
+                dist,
+                focus: Focus::Mario,
+            });
+        }
+    }
+
+    /// Switches to a free-flying camera at the current view, steered by
+    /// WASD/Space/Shift (via `update`'s `camera_move`) and, while
+    /// [Self::set_mouse_look] is enabled, by mouse motion.
+    pub fn enable_flycam(&mut self) {
+        if self.flycam.is_some() {
+            return;
+        }
+        let (Some(pos), Some([pitch, yaw, _])) = (self.current_pos(), self.current_angle()) else {
+            return;
+        };
+        self.drag_start = None;
+        self.camera_override = None;
+        self.flycam = Some(FlycamState { pos, yaw, pitch });
+    }
+
+    pub fn update(
+        &mut self,
+        layout: &impl MemoryLayout,
+        memory: &impl MemoryRead,
+        camera_move: [f32; 3],
+    ) -> Result<(), VizError> {
+        let in_game_camera_addr = layout.symbol_address("gLakituState")?;
+        let in_game_camera: InGameCamera =
+            InGameCamera::reader(layout)?.read(memory, in_game_camera_addr)?;
+        self.in_game_camera = Some(in_game_camera.clone());
+
+        let mario_pos = layout
+            .global_path("gMarioState.pos")?
+            .read(memory)?
+            .try_as_f32_3()?;
+        self.mario_pos = Some(mario_pos);
+
+        if let Some(flycam) = &mut self.flycam {
+            let now = Instant::now();
+            let dt = match self.prev_update.replace(now) {
+                Some(prev) => now.saturating_duration_since(prev).as_secs_f32(),
+                None => 0.0,
+            };
+
+            let [mut dx, mut dy, mut dz] = camera_move;
+            let mag = (dx * dx + dy * dy + dz * dz).sqrt();
+            if mag > 1.0 {
+                dx /= mag;
+                dy /= mag;
+                dz /= mag;
+            }
+            let step = self.speed * dt;
+            dx *= step;
+            dy *= step;
+            dz *= step;
+
+            let yaw_rotate = Matrixf::rotate_xyz_and_translate(
+                [0.0, 0.0, 0.0],
+                [Wrapping(0), Wrapping(-0x8000) + flycam.yaw, Wrapping(0)],
+            );
+            let move_world = &yaw_rotate * [dx, dy, dz, 0.0];
+            flycam.pos[0] += move_world[0];
+            flycam.pos[1] += move_world[1];
+            flycam.pos[2] += move_world[2];
+
+            let xz = coss(flycam.pitch);
+            let forward = [
+                xz * sins(flycam.yaw),
+                sins(flycam.pitch),
+                xz * coss(flycam.yaw),
+            ];
+            self.camera = Camera::LookAt(LookAtCamera {
+                pos: flycam.pos,
+                focus: [
+                    flycam.pos[0] + forward[0],
+                    flycam.pos[1] + forward[1],
+                    flycam.pos[2] + forward[2],
+                ],
+                roll: Wrapping(0),
+            });
+            return Ok(());
+        }
+        self.prev_update = None;
+
+        if let (Some(drag_state), Some(mouse_pos)) = (&self.drag_start, self.mouse_pos) {
+            let drag = [
+                mouse_pos[0] - drag_state.mouse_pos[0],
+                mouse_pos[1] - drag_state.mouse_pos[1],
+            ];
+            let drag_dist = (drag[0] * drag[0] + drag[1] * drag[1]).sqrt();
+            if self.camera_override.is_some() || drag_dist > 10.0 {
+                let [pitch0, yaw0, _] = drag_state.angle;
+                let pitch = (pitch0 - Wrapping((drag[1] * 50.0) as i32 as i16))
+                    .clamp(Wrapping(-0x3FF0), Wrapping(0x3FF0));
+                let yaw = yaw0 - Wrapping((drag[0] * 50.0) as i32 as i16);
+                let angle = [pitch, yaw, Wrapping(0)];
+
+                if let Some(camera_override) = self.get_or_init_override() {
+                    camera_override.angle = angle;
+                }
+            }
+        }
+
+        self.camera = if let Some(camera_override) = &self.camera_override {
+            let [pitch, yaw, _] = camera_override.angle;
+            let focus = match camera_override.focus {
+                Focus::InGame => in_game_camera.focus,
+                Focus::Mario => mario_pos,
+            };
+
+            let r = camera_override.dist;
+            let xz = r * coss(pitch);
+
+            let dx = xz * sins(yaw);
+            let dy = r * sins(pitch);
+            let dz = xz * coss(yaw);
+
+            let pos = [focus[0] - dx, focus[1] - dy, focus[2] - dz];
+
+            Camera::LookAt(LookAtCamera {
+                pos,
+                focus,
+                roll: Wrapping(0),
+            })
+        } else {
+            Camera::InGame
+        };
+
+        Ok(())
+    }
+}
@@ -5,6 +5,8 @@ use enum_map::{Enum, EnumMap};
 use wafel_viz::{Element, Rect2, TransparencyHint, Vec2, Vec4, VizScene};
 use wgpu::util::DeviceExt;
 
+use crate::pipelines::BlendMode;
+
 #[derive(Debug, Clone, Copy, PartialEq, Default, Zeroable, Pod)]
 #[repr(C)]
 pub struct ColorVertex {
@@ -184,16 +186,30 @@ pub enum TriangleTransparency {
 pub enum BufferId {
     Point {
         transparent: bool,
+        blend_mode: BlendMode,
     },
     Line {
         transparent: bool,
+        blend_mode: BlendMode,
     },
     Triangle {
         transparency: TriangleTransparency,
         surface_gradient: bool,
+        blend_mode: BlendMode,
     },
 }
 
+/// Returns the [BlendMode] a buffer should use for an element with the given blend mode and
+/// transparency. Opaque elements (`transparent == false`) always overwrite the framebuffer, so
+/// their blend mode is ignored and bucketed as [BlendMode::AlphaBlend].
+fn buffer_blend_mode(transparent: bool, blend_mode: wafel_viz::BlendMode) -> BlendMode {
+    if transparent {
+        blend_mode.into()
+    } else {
+        BlendMode::AlphaBlend
+    }
+}
+
 #[derive(Debug)]
 pub struct PerFrameData {
     pub output_size: Vec2,
@@ -206,6 +222,10 @@ pub struct PerFrameData {
 
     pub transform_bind_group: wgpu::BindGroup,
     pub buffers: EnumMap<BufferId, Option<(u32, wgpu::Buffer)>>,
+
+    /// Bundled replay of the opaque draws and the transparent draws, in that order. `None` while
+    /// GPU profiling is enabled, since bundles can't contain timestamp/statistics queries.
+    pub bundles: Option<(wgpu::RenderBundle, wgpu::RenderBundle)>,
 }
 
 impl PerFrameData {
@@ -245,8 +265,10 @@ impl PerFrameData {
         for element in &scene.elements {
             match element {
                 Element::Point(point) => {
+                    let transparent = point.color[3] < 1.0;
                     let buffer_id = BufferId::Point {
-                        transparent: point.color[3] < 1.0,
+                        transparent,
+                        blend_mode: buffer_blend_mode(transparent, point.blend_mode),
                     };
                     counts[buffer_id] += 1;
                     buffer_data[buffer_id].extend(cast_slice(&[PointInstance {
@@ -256,8 +278,10 @@ impl PerFrameData {
                     }]));
                 }
                 Element::Line(line) => {
+                    let transparent = line.color[3] < 1.0;
                     let buffer_id = BufferId::Line {
-                        transparent: line.color[3] < 1.0,
+                        transparent,
+                        blend_mode: buffer_blend_mode(transparent, line.blend_mode),
                     };
                     counts[buffer_id] += 2;
                     buffer_data[buffer_id].extend(cast_slice(&[
@@ -272,7 +296,8 @@ impl PerFrameData {
                     ]));
                 }
                 Element::Triangle(triangle) => {
-                    let transparency = if triangle.color[3] >= 1.0 {
+                    let transparent = triangle.color[3] < 1.0;
+                    let transparency = if !transparent {
                         TriangleTransparency::Opaque
                     } else {
                         match triangle.transparency_hint {
@@ -285,6 +310,7 @@ impl PerFrameData {
                     let buffer_id = BufferId::Triangle {
                         transparency,
                         surface_gradient: triangle.surface_gradient,
+                        blend_mode: buffer_blend_mode(transparent, triangle.blend_mode),
                     };
                     counts[buffer_id] += 3;
                     buffer_data[buffer_id].extend(cast_slice(&[
@@ -327,6 +353,7 @@ impl PerFrameData {
             f3d_post_depth_cmd_range: post_depth_cmd..num_cmds,
             transform_bind_group,
             buffers,
+            bundles: None,
         }
     }
 }
@@ -4,8 +4,10 @@
 
 #![warn(rust_2018_idioms, missing_debug_implementations, missing_docs)]
 
+pub use profile::{StageProfiler, StageStats, STAGES as PROFILE_STAGES};
 pub use renderer::VizRenderer;
 
 mod data;
 mod pipelines;
+mod profile;
 mod renderer;
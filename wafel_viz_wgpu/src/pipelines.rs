@@ -6,15 +6,83 @@ use crate::data::{ColorVertex, PointInstance, PointVertex};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Enum)]
 pub enum PipelineId {
-    Line,
-    Point,
+    Line {
+        blend_mode: BlendMode,
+    },
+    Point {
+        blend_mode: BlendMode,
+    },
     Triangle {
         surface_gradient: bool,
         depth_write: bool,
         color_write: bool,
+        blend_mode: BlendMode,
     },
 }
 
+/// Mirrors [wafel_viz::BlendMode] with the [Enum] derive needed to key
+/// [PipelineId]/[crate::data::BufferId] by blend mode.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Enum)]
+pub enum BlendMode {
+    AlphaBlend,
+    Additive,
+    PremultipliedAlpha,
+}
+
+impl From<wafel_viz::BlendMode> for BlendMode {
+    fn from(blend_mode: wafel_viz::BlendMode) -> Self {
+        match blend_mode {
+            wafel_viz::BlendMode::AlphaBlend => Self::AlphaBlend,
+            wafel_viz::BlendMode::Additive => Self::Additive,
+            wafel_viz::BlendMode::PremultipliedAlpha => Self::PremultipliedAlpha,
+        }
+    }
+}
+
+/// Returns the explicit color/alpha [wgpu::BlendComponent]s for a [BlendMode], drawn directly
+/// from the WebGPU blend API rather than the named [wgpu::BlendState] presets so that additional
+/// modes can be added here without reaching for new constants.
+fn blend_state(blend_mode: BlendMode) -> wgpu::BlendState {
+    match blend_mode {
+        BlendMode::AlphaBlend => wgpu::BlendState {
+            color: wgpu::BlendComponent {
+                src_factor: wgpu::BlendFactor::SrcAlpha,
+                dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+                operation: wgpu::BlendOperation::Add,
+            },
+            alpha: wgpu::BlendComponent {
+                src_factor: wgpu::BlendFactor::One,
+                dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+                operation: wgpu::BlendOperation::Add,
+            },
+        },
+        BlendMode::Additive => wgpu::BlendState {
+            color: wgpu::BlendComponent {
+                src_factor: wgpu::BlendFactor::SrcAlpha,
+                dst_factor: wgpu::BlendFactor::One,
+                operation: wgpu::BlendOperation::Add,
+            },
+            alpha: wgpu::BlendComponent {
+                src_factor: wgpu::BlendFactor::One,
+                dst_factor: wgpu::BlendFactor::One,
+                operation: wgpu::BlendOperation::Add,
+            },
+        },
+        BlendMode::PremultipliedAlpha => wgpu::BlendState {
+            color: wgpu::BlendComponent {
+                src_factor: wgpu::BlendFactor::One,
+                dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+                operation: wgpu::BlendOperation::Add,
+            },
+            alpha: wgpu::BlendComponent {
+                src_factor: wgpu::BlendFactor::One,
+                dst_factor: wgpu::BlendFactor::OneMinusSrcAlpha,
+                operation: wgpu::BlendOperation::Add,
+            },
+        },
+    }
+}
+
 pub fn create_pipelines(
     device: &wgpu::Device,
     transform_bind_group_layout: &wgpu::BindGroupLayout,
@@ -26,34 +94,39 @@ pub fn create_pipelines(
     for i in 0..PipelineId::LENGTH {
         let pipeline_id = PipelineId::from_usize(i);
         let pipeline = match pipeline_id {
-            PipelineId::Line => create_line_pipeline(
+            PipelineId::Line { blend_mode } => create_line_pipeline(
                 device,
                 transform_bind_group_layout,
                 output_format,
+                blend_mode,
                 msaa_samples,
             ),
-            PipelineId::Point => create_point_pipeline(
+            PipelineId::Point { blend_mode } => create_point_pipeline(
                 device,
                 transform_bind_group_layout,
                 output_format,
+                blend_mode,
                 msaa_samples,
             ),
             PipelineId::Triangle {
                 surface_gradient: true,
                 depth_write,
                 color_write,
+                blend_mode,
             } => create_surface_pipeline(
                 device,
                 transform_bind_group_layout,
                 output_format,
                 color_write,
                 depth_write,
+                blend_mode,
                 msaa_samples,
             ),
             PipelineId::Triangle {
                 surface_gradient: false,
                 depth_write,
                 color_write,
+                blend_mode,
             } => create_color_pipeline(
                 device,
                 transform_bind_group_layout,
@@ -62,6 +135,7 @@ pub fn create_pipelines(
                 depth_write,
                 true,
                 wgpu::PrimitiveTopology::TriangleList,
+                blend_mode,
                 msaa_samples,
             ),
         };
@@ -75,6 +149,7 @@ fn create_line_pipeline(
     device: &wgpu::Device,
     transform_bind_group_layout: &wgpu::BindGroupLayout,
     output_format: wgpu::TextureFormat,
+    blend_mode: BlendMode,
     msaa_samples: u32,
 ) -> wgpu::RenderPipeline {
     let shader_module =
@@ -114,7 +189,7 @@ fn create_line_pipeline(
             entry_point: "fs_main",
             targets: &[Some(wgpu::ColorTargetState {
                 format: output_format,
-                blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                blend: Some(blend_state(blend_mode)),
                 write_mask: wgpu::ColorWrites::ALL,
             })],
         }),
@@ -126,6 +201,7 @@ fn create_point_pipeline(
     device: &wgpu::Device,
     transform_bind_group_layout: &wgpu::BindGroupLayout,
     output_format: wgpu::TextureFormat,
+    blend_mode: BlendMode,
     msaa_samples: u32,
 ) -> wgpu::RenderPipeline {
     let shader_module = device.create_shader_module(wgpu::include_wgsl!("../shaders/point.wgsl"));
@@ -164,7 +240,7 @@ fn create_point_pipeline(
             entry_point: "fs_main",
             targets: &[Some(wgpu::ColorTargetState {
                 format: output_format,
-                blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                blend: Some(blend_state(blend_mode)),
                 write_mask: wgpu::ColorWrites::ALL,
             })],
         }),
@@ -172,12 +248,14 @@ fn create_point_pipeline(
     })
 }
 
+#[allow(clippy::too_many_arguments)]
 fn create_surface_pipeline(
     device: &wgpu::Device,
     transform_bind_group_layout: &wgpu::BindGroupLayout,
     output_format: wgpu::TextureFormat,
     color_write_enabled: bool,
     depth_write_enabled: bool,
+    blend_mode: BlendMode,
     msaa_samples: u32,
 ) -> wgpu::RenderPipeline {
     let shader_module = device.create_shader_module(wgpu::include_wgsl!("../shaders/surface.wgsl"));
@@ -213,7 +291,7 @@ fn create_surface_pipeline(
             entry_point: "fs_main",
             targets: &[Some(wgpu::ColorTargetState {
                 format: output_format,
-                blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                blend: Some(blend_state(blend_mode)),
                 write_mask: if color_write_enabled {
                     wgpu::ColorWrites::ALL
                 } else {
@@ -234,6 +312,7 @@ fn create_color_pipeline(
     depth_write_enabled: bool,
     depth_compare_enabled: bool,
     topology: wgpu::PrimitiveTopology,
+    blend_mode: BlendMode,
     msaa_samples: u32,
 ) -> wgpu::RenderPipeline {
     let shader_module = device.create_shader_module(wgpu::include_wgsl!("../shaders/color.wgsl"));
@@ -276,7 +355,7 @@ fn create_color_pipeline(
             entry_point: "fs_main",
             targets: &[Some(wgpu::ColorTargetState {
                 format: output_format,
-                blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                blend: Some(blend_state(blend_mode)),
                 write_mask: if color_write_enabled {
                     wgpu::ColorWrites::ALL
                 } else {
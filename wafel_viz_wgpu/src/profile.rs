@@ -0,0 +1,192 @@
+//! Optional per-stage GPU timing for [VizRenderer](crate::VizRenderer).
+//!
+//! [StageProfiler] writes a timestamp before and after each named render stage (using
+//! `wgpu::Features::TIMESTAMP_QUERY_INSIDE_PASSES`) and, when available, wraps the stage in a
+//! pipeline-statistics query (`wgpu::Features::PIPELINE_STATISTICS_QUERY`). The results are
+//! resolved after submission and reported as a duration plus primitive/vertex-invocation counts
+//! per stage.
+
+use std::{collections::HashMap, time::Duration};
+
+/// The stages instrumented by [StageProfiler], in the order they're drawn by
+/// [VizRenderer::render](crate::VizRenderer::render).
+pub const STAGES: &[&str] = &[
+    "pre_depth_f3d",
+    "opaque",
+    "depth_f3d",
+    "transparent_lines_points",
+    "wall_hitboxes",
+    "transparent_triangles",
+    "post_depth_f3d",
+];
+
+/// GPU timing and pipeline-statistics counts for a single stage.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StageStats {
+    /// Wall-clock GPU time spent in the stage.
+    pub duration: Duration,
+    /// Number of primitives output by the clipper during the stage, if
+    /// `PIPELINE_STATISTICS_QUERY` is supported.
+    pub primitives: Option<u64>,
+    /// Number of vertex shader invocations during the stage, if `PIPELINE_STATISTICS_QUERY` is
+    /// supported.
+    pub vertex_invocations: Option<u64>,
+}
+
+/// Writes and resolves GPU timing/statistics queries around each render stage.
+#[derive(Debug)]
+pub struct StageProfiler {
+    timestamp_query_set: wgpu::QuerySet,
+    pipeline_query_set: Option<wgpu::QuerySet>,
+    timestamp_period_ns: f64,
+    resolve_buffer: wgpu::Buffer,
+    readback_buffer: wgpu::Buffer,
+}
+
+const TIMESTAMPS_PER_STAGE: u64 = 2;
+const PIPELINE_STATS_PER_STAGE: u64 = 2; // clipper primitives + vertex invocations
+
+impl StageProfiler {
+    /// Required device features for timestamp queries only (no pipeline statistics).
+    pub fn required_features() -> wgpu::Features {
+        wgpu::Features::TIMESTAMP_QUERY | wgpu::Features::TIMESTAMP_QUERY_INSIDE_PASSES
+    }
+
+    /// Creates a profiler if the device supports at least [Self::required_features]. Pipeline
+    /// statistics are included automatically if the device also supports
+    /// `PIPELINE_STATISTICS_QUERY`.
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Option<Self> {
+        let features = device.features();
+        if !features.contains(Self::required_features()) {
+            return None;
+        }
+        let supports_pipeline_stats = features.contains(wgpu::Features::PIPELINE_STATISTICS_QUERY);
+
+        let timestamp_count = STAGES.len() as u32 * TIMESTAMPS_PER_STAGE as u32;
+        let timestamp_query_set = device.create_query_set(&wgpu::QuerySetDescriptor {
+            label: Some("viz-stage-timestamps"),
+            ty: wgpu::QueryType::Timestamp,
+            count: timestamp_count,
+        });
+
+        let pipeline_query_set = supports_pipeline_stats.then(|| {
+            device.create_query_set(&wgpu::QuerySetDescriptor {
+                label: Some("viz-stage-pipeline-stats"),
+                ty: wgpu::QueryType::PipelineStatistics(
+                    wgpu::PipelineStatisticsTypes::CLIPPER_PRIMITIVES_OUT
+                        | wgpu::PipelineStatisticsTypes::VERTEX_SHADER_INVOCATIONS,
+                ),
+                count: STAGES.len() as u32,
+            })
+        });
+
+        let pipeline_stats_size = if supports_pipeline_stats {
+            STAGES.len() as u64 * PIPELINE_STATS_PER_STAGE * 8
+        } else {
+            0
+        };
+        let buffer_size = timestamp_count as u64 * 8 + pipeline_stats_size;
+
+        let resolve_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("viz-stage-profiler-resolve"),
+            size: buffer_size,
+            usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let readback_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("viz-stage-profiler-readback"),
+            size: buffer_size,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        Some(Self {
+            timestamp_query_set,
+            pipeline_query_set,
+            timestamp_period_ns: queue.get_timestamp_period() as f64,
+            resolve_buffer,
+            readback_buffer,
+        })
+    }
+
+    /// Writes the start-of-stage timestamp/statistics query. Does not check whether another
+    /// stage is already open.
+    pub(crate) fn begin_stage<'r>(&'r self, rp: &mut wgpu::RenderPass<'r>, stage: usize) {
+        rp.write_timestamp(&self.timestamp_query_set, stage as u32 * 2);
+        if let Some(pipeline_query_set) = &self.pipeline_query_set {
+            rp.begin_pipeline_statistics_query(pipeline_query_set, stage as u32);
+        }
+    }
+
+    /// Writes the end-of-stage timestamp/statistics query.
+    pub(crate) fn end_stage<'r>(&'r self, rp: &mut wgpu::RenderPass<'r>, stage: usize) {
+        rp.write_timestamp(&self.timestamp_query_set, stage as u32 * 2 + 1);
+        if self.pipeline_query_set.is_some() {
+            rp.end_pipeline_statistics_query();
+        }
+    }
+
+    /// Resolves the queries written during the render pass into the readback buffer. Must be
+    /// called with the same encoder that contains the instrumented render pass, prior to
+    /// submission.
+    pub(crate) fn resolve(&self, encoder: &mut wgpu::CommandEncoder) {
+        let timestamp_count = STAGES.len() as u32 * TIMESTAMPS_PER_STAGE as u32;
+        encoder.resolve_query_set(&self.timestamp_query_set, 0..timestamp_count, &self.resolve_buffer, 0);
+        if let Some(pipeline_query_set) = &self.pipeline_query_set {
+            let offset = timestamp_count as u64 * 8;
+            encoder.resolve_query_set(
+                pipeline_query_set,
+                0..STAGES.len() as u32,
+                &self.resolve_buffer,
+                offset,
+            );
+        }
+        encoder.copy_buffer_to_buffer(&self.resolve_buffer, 0, &self.readback_buffer, 0, self.readback_buffer.size());
+    }
+
+    /// Maps and reads back the resolved queries. Blocks on the device until the results are
+    /// available, so should be called after [wgpu::Queue::submit].
+    pub fn read_stats(&self, device: &wgpu::Device) -> HashMap<&'static str, StageStats> {
+        let slice = self.readback_buffer.slice(..);
+        slice.map_async(wgpu::MapMode::Read, |_| {});
+        device.poll(wgpu::Maintain::Wait);
+
+        let mut stats = HashMap::new();
+        {
+            let data = slice.get_mapped_range();
+            let timestamps: &[u64] = bytemuck::cast_slice(&data[..STAGES.len() * 16]);
+            let pipeline_stats: &[u64] = if self.pipeline_query_set.is_some() {
+                bytemuck::cast_slice(&data[STAGES.len() * 16..])
+            } else {
+                &[]
+            };
+
+            for (i, &name) in STAGES.iter().enumerate() {
+                let start = timestamps[i * 2];
+                let end = timestamps[i * 2 + 1];
+                let duration_ns = (end.saturating_sub(start)) as f64 * self.timestamp_period_ns;
+
+                let (primitives, vertex_invocations) = if pipeline_stats.is_empty() {
+                    (None, None)
+                } else {
+                    (
+                        Some(pipeline_stats[i * 2 + 1]),
+                        Some(pipeline_stats[i * 2]),
+                    )
+                };
+
+                stats.insert(
+                    name,
+                    StageStats {
+                        duration: Duration::from_nanos(duration_ns.round() as u64),
+                        primitives,
+                        vertex_invocations,
+                    },
+                );
+            }
+        }
+        self.readback_buffer.unmap();
+
+        stats
+    }
+}
@@ -1,16 +1,68 @@
 use std::collections::HashMap;
 
+use enum_map::Enum;
 use fast3d::render::F3DRenderer;
 use wafel_viz::{Rect2, Vec2, Viewport, VizScene};
 
 use crate::{
     data::{BufferId, PerFrameData, StaticData, TriangleTransparency},
-    pipelines::{create_pipelines, PipelineId},
+    pipelines::{create_pipelines, BlendMode, PipelineId},
+    profile::{StageProfiler, StageStats},
 };
 
+/// All [BlendMode] variants, for looping over the transparent buffers that vary by blend mode.
+const BLEND_MODES: [BlendMode; BlendMode::LENGTH] =
+    [BlendMode::AlphaBlend, BlendMode::Additive, BlendMode::PremultipliedAlpha];
+
 // TODO: Specify frag_depth as uniform / push constant, combine color_decal.wgsl and
 // color.wgsl, use for wall hitboxes instead of calculating by hand
 
+/// The subset of `wgpu::RenderPass`/`wgpu::RenderBundleEncoder` methods needed to draw a
+/// [BufferId] buffer, so that [VizRenderer::draw_buffer] can record either directly into a pass
+/// or into a bundle.
+trait DrawTarget<'r> {
+    fn set_pipeline(&mut self, pipeline: &'r wgpu::RenderPipeline);
+    fn set_bind_group(&mut self, index: u32, bind_group: &'r wgpu::BindGroup, offsets: &[u32]);
+    fn set_vertex_buffer(&mut self, slot: u32, slice: wgpu::BufferSlice<'r>);
+    fn draw(&mut self, vertices: std::ops::Range<u32>, instances: std::ops::Range<u32>);
+}
+
+impl<'r> DrawTarget<'r> for wgpu::RenderPass<'r> {
+    fn set_pipeline(&mut self, pipeline: &'r wgpu::RenderPipeline) {
+        wgpu::RenderPass::set_pipeline(self, pipeline);
+    }
+
+    fn set_bind_group(&mut self, index: u32, bind_group: &'r wgpu::BindGroup, offsets: &[u32]) {
+        wgpu::RenderPass::set_bind_group(self, index, bind_group, offsets);
+    }
+
+    fn set_vertex_buffer(&mut self, slot: u32, slice: wgpu::BufferSlice<'r>) {
+        wgpu::RenderPass::set_vertex_buffer(self, slot, slice);
+    }
+
+    fn draw(&mut self, vertices: std::ops::Range<u32>, instances: std::ops::Range<u32>) {
+        wgpu::RenderPass::draw(self, vertices, instances);
+    }
+}
+
+impl<'r> DrawTarget<'r> for wgpu::RenderBundleEncoder<'r> {
+    fn set_pipeline(&mut self, pipeline: &'r wgpu::RenderPipeline) {
+        wgpu::RenderBundleEncoder::set_pipeline(self, pipeline);
+    }
+
+    fn set_bind_group(&mut self, index: u32, bind_group: &'r wgpu::BindGroup, offsets: &[u32]) {
+        wgpu::RenderBundleEncoder::set_bind_group(self, index, bind_group, offsets);
+    }
+
+    fn set_vertex_buffer(&mut self, slot: u32, slice: wgpu::BufferSlice<'r>) {
+        wgpu::RenderBundleEncoder::set_vertex_buffer(self, slot, slice);
+    }
+
+    fn draw(&mut self, vertices: std::ops::Range<u32>, instances: std::ops::Range<u32>) {
+        wgpu::RenderBundleEncoder::draw(self, vertices, instances);
+    }
+}
+
 /// A wgpu renderer for [VizScene].
 #[derive(Debug)]
 pub struct VizRenderer {
@@ -18,6 +70,9 @@ pub struct VizRenderer {
     static_data: StaticData,
     pipelines: HashMap<PipelineId, wgpu::RenderPipeline>,
     per_frame_data: Option<PerFrameData>,
+    profiler: Option<StageProfiler>,
+    output_format: wgpu::TextureFormat,
+    msaa_samples: u32,
 }
 
 impl VizRenderer {
@@ -40,6 +95,49 @@ impl VizRenderer {
             static_data,
             pipelines,
             per_frame_data: None,
+            profiler: None,
+            output_format,
+            msaa_samples,
+        }
+    }
+
+    /// Enables per-stage GPU timing if the device supports [StageProfiler::required_features],
+    /// returning whether it was enabled. Pipeline-statistics counts are included automatically
+    /// if the device also supports `PIPELINE_STATISTICS_QUERY`.
+    ///
+    /// While profiling is enabled, [Self::prepare] records no render bundles (bundles can't
+    /// contain queries), so every draw is encoded directly into the render pass each frame.
+    pub fn enable_profiling(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) -> bool {
+        self.profiler = StageProfiler::new(device, queue);
+        self.profiler.is_some()
+    }
+
+    /// Disables per-stage GPU timing.
+    pub fn disable_profiling(&mut self) {
+        self.profiler = None;
+    }
+
+    /// Returns whether profiling is currently enabled.
+    pub fn profiling_enabled(&self) -> bool {
+        self.profiler.is_some()
+    }
+
+    /// Reads back the GPU timing/statistics queries that [Self::resolve_profiling] resolved after
+    /// the last call to [Self::render]. Blocks until the results are available, so should only be called a frame
+    /// or more after the corresponding [wgpu::Queue::submit] to avoid stalling the GPU pipeline.
+    ///
+    /// Returns `None` if profiling is not enabled (see [Self::enable_profiling]).
+    pub fn stage_timings(&self, device: &wgpu::Device) -> Option<HashMap<&'static str, StageStats>> {
+        self.profiler.as_ref().map(|profiler| profiler.read_stats(device))
+    }
+
+    /// Resolves the GPU timing/statistics queries written during [Self::render] into a readback
+    /// buffer. Must be called with the same encoder as the render pass passed to [Self::render],
+    /// after that pass has ended and before the encoder is submitted. No-op if profiling is not
+    /// enabled.
+    pub fn resolve_profiling(&self, encoder: &mut wgpu::CommandEncoder) {
+        if let Some(profiler) = &self.profiler {
+            profiler.resolve(encoder);
         }
     }
 
@@ -73,19 +171,30 @@ impl VizRenderer {
             );
         }
 
-        self.per_frame_data = Some(PerFrameData::create(
+        let mut per_frame_data = PerFrameData::create(
             device,
             &self.static_data,
             scene,
             output_size_logical,
             vieport,
             scale_factor,
-        ));
+        );
+
+        // Bundling the static draw sequence speeds up re-recording it every frame, but a bundle
+        // can't contain the timestamp/pipeline-statistics queries used for profiling.
+        if self.profiler.is_none() {
+            per_frame_data.bundles = Some((
+                self.record_opaque_bundle(device, &per_frame_data),
+                self.record_transparent_bundle(device, &per_frame_data),
+            ));
+        }
+
+        self.per_frame_data = Some(per_frame_data);
     }
 
-    fn draw_buffer<'r>(
+    fn draw_buffer<'r, T: DrawTarget<'r>>(
         &'r self,
-        rp: &mut wgpu::RenderPass<'r>,
+        target: &mut T,
         render_data: &'r PerFrameData,
         pipeline_id: PipelineId,
         buffer_id: BufferId,
@@ -95,20 +204,181 @@ impl VizRenderer {
 
             if matches!(buffer_id, BufferId::Point { .. }) {
                 // Points use instanced rendering.
-                rp.set_pipeline(pipeline);
-                rp.set_bind_group(0, &render_data.transform_bind_group, &[]);
-                rp.set_vertex_buffer(0, buffer.slice(..));
-                rp.set_vertex_buffer(1, self.static_data.point_vertex_buffer.1.slice(..));
-                rp.draw(0..self.static_data.point_vertex_buffer.0, 0..*count);
+                target.set_pipeline(pipeline);
+                target.set_bind_group(0, &render_data.transform_bind_group, &[]);
+                target.set_vertex_buffer(0, buffer.slice(..));
+                target.set_vertex_buffer(1, self.static_data.point_vertex_buffer.1.slice(..));
+                target.draw(0..self.static_data.point_vertex_buffer.0, 0..*count);
             } else {
-                rp.set_pipeline(pipeline);
-                rp.set_bind_group(0, &render_data.transform_bind_group, &[]);
-                rp.set_vertex_buffer(0, buffer.slice(..));
-                rp.draw(0..*count, 0..1);
+                target.set_pipeline(pipeline);
+                target.set_bind_group(0, &render_data.transform_bind_group, &[]);
+                target.set_vertex_buffer(0, buffer.slice(..));
+                target.draw(0..*count, 0..1);
             }
         }
     }
 
+    /// Records the opaque triangle/line/point draws (the portion of [Self::render] between the
+    /// pre-depth and depth-tested F3D commands) into a bundle that can be replayed with a single
+    /// [wgpu::RenderPass::execute_bundles] call.
+    fn record_opaque_bundle(
+        &self,
+        device: &wgpu::Device,
+        render_data: &PerFrameData,
+    ) -> wgpu::RenderBundle {
+        let mut encoder = device.create_render_bundle_encoder(&wgpu::RenderBundleEncoderDescriptor {
+            label: Some("viz-opaque-bundle"),
+            color_formats: &[Some(self.output_format)],
+            depth_stencil: Some(wgpu::RenderBundleDepthStencil {
+                format: wgpu::TextureFormat::Depth24Plus,
+                depth_read_only: false,
+                stencil_read_only: true,
+            }),
+            sample_count: self.msaa_samples,
+            multiview: None,
+        });
+
+        for surface_gradient in [true, false] {
+            self.draw_buffer(
+                &mut encoder,
+                render_data,
+                PipelineId::Triangle {
+                    surface_gradient,
+                    depth_write: true,
+                    color_write: true,
+                    blend_mode: BlendMode::AlphaBlend,
+                },
+                BufferId::Triangle {
+                    transparency: TriangleTransparency::Opaque,
+                    surface_gradient,
+                    blend_mode: BlendMode::AlphaBlend,
+                },
+            );
+        }
+        self.draw_buffer(
+            &mut encoder,
+            render_data,
+            PipelineId::Line {
+                blend_mode: BlendMode::AlphaBlend,
+            },
+            BufferId::Line {
+                transparent: false,
+                blend_mode: BlendMode::AlphaBlend,
+            },
+        );
+        self.draw_buffer(
+            &mut encoder,
+            render_data,
+            PipelineId::Point {
+                blend_mode: BlendMode::AlphaBlend,
+            },
+            BufferId::Point {
+                transparent: false,
+                blend_mode: BlendMode::AlphaBlend,
+            },
+        );
+
+        encoder.finish(&wgpu::RenderBundleDescriptor {
+            label: Some("viz-opaque-bundle"),
+        })
+    }
+
+    /// Records the transparent line/point/wall-hitbox/triangle draws (the portion of
+    /// [Self::render] after the depth-tested F3D commands) into a bundle that can be replayed
+    /// with a single [wgpu::RenderPass::execute_bundles] call.
+    fn record_transparent_bundle(
+        &self,
+        device: &wgpu::Device,
+        render_data: &PerFrameData,
+    ) -> wgpu::RenderBundle {
+        let mut encoder = device.create_render_bundle_encoder(&wgpu::RenderBundleEncoderDescriptor {
+            label: Some("viz-transparent-bundle"),
+            color_formats: &[Some(self.output_format)],
+            depth_stencil: Some(wgpu::RenderBundleDepthStencil {
+                format: wgpu::TextureFormat::Depth24Plus,
+                depth_read_only: false,
+                stencil_read_only: true,
+            }),
+            sample_count: self.msaa_samples,
+            multiview: None,
+        });
+
+        for blend_mode in BLEND_MODES {
+            self.draw_buffer(
+                &mut encoder,
+                render_data,
+                PipelineId::Line { blend_mode },
+                BufferId::Line {
+                    transparent: true,
+                    blend_mode,
+                },
+            );
+            self.draw_buffer(
+                &mut encoder,
+                render_data,
+                PipelineId::Point { blend_mode },
+                BufferId::Point {
+                    transparent: true,
+                    blend_mode,
+                },
+            );
+
+            for color_write in [false, true] {
+                for surface_gradient in [false, true] {
+                    self.draw_buffer(
+                        &mut encoder,
+                        render_data,
+                        PipelineId::Triangle {
+                            surface_gradient,
+                            depth_write: true,
+                            color_write,
+                            blend_mode,
+                        },
+                        BufferId::Triangle {
+                            transparency: TriangleTransparency::TransparentWallHitbox,
+                            surface_gradient,
+                            blend_mode,
+                        },
+                    );
+                }
+            }
+
+            for surface_gradient in [false, true] {
+                self.draw_buffer(
+                    &mut encoder,
+                    render_data,
+                    PipelineId::Triangle {
+                        surface_gradient,
+                        depth_write: false,
+                        color_write: true,
+                        blend_mode,
+                    },
+                    BufferId::Triangle {
+                        transparency: TriangleTransparency::Transparent,
+                        surface_gradient,
+                        blend_mode,
+                    },
+                );
+            }
+        }
+
+        encoder.finish(&wgpu::RenderBundleDescriptor {
+            label: Some("viz-transparent-bundle"),
+        })
+    }
+
+    fn begin_stage<'r>(&'r self, rp: &mut wgpu::RenderPass<'r>, stage: usize) {
+        if let Some(profiler) = &self.profiler {
+            profiler.begin_stage(rp, stage);
+        }
+    }
+
+    fn end_stage<'r>(&'r self, rp: &mut wgpu::RenderPass<'r>, stage: usize) {
+        if let Some(profiler) = &self.profiler {
+            profiler.end_stage(rp, stage);
+        }
+    }
+
     /// Renders a [VizScene] that was provided to [Self::prepare].
     pub fn render<'r>(&'r self, rp: &mut wgpu::RenderPass<'r>) {
         let render_data = self
@@ -122,8 +392,10 @@ impl VizRenderer {
         }
 
         // Execute F3D commands which are prior to enabling depth test (e.g. skybox).
+        self.begin_stage(rp, 0); // pre_depth_f3d
         self.f3d_renderer
             .render_command_range(rp, render_data.f3d_pre_depth_cmd_range.clone());
+        self.end_stage(rp, 0);
 
         // Set the viewport and scissor rect.
         let scaled_viewport = render_data.viewport.scale(scale_factor);
@@ -150,99 +422,151 @@ impl VizRenderer {
         );
 
         // Draw opaque triangles, lines, then points.
-        for surface_gradient in [true, false] {
+        self.begin_stage(rp, 1); // opaque
+        if let Some((opaque_bundle, _)) = &render_data.bundles {
+            rp.execute_bundles([opaque_bundle]);
+        } else {
+            for surface_gradient in [true, false] {
+                self.draw_buffer(
+                    rp,
+                    render_data,
+                    PipelineId::Triangle {
+                        surface_gradient,
+                        depth_write: true,
+                        color_write: true,
+                        blend_mode: BlendMode::AlphaBlend,
+                    },
+                    BufferId::Triangle {
+                        transparency: TriangleTransparency::Opaque,
+                        surface_gradient,
+                        blend_mode: BlendMode::AlphaBlend,
+                    },
+                );
+            }
             self.draw_buffer(
                 rp,
                 render_data,
-                PipelineId::Triangle {
-                    surface_gradient,
-                    depth_write: true,
-                    color_write: true,
+                PipelineId::Line {
+                    blend_mode: BlendMode::AlphaBlend,
                 },
-                BufferId::Triangle {
-                    transparency: TriangleTransparency::Opaque,
-                    surface_gradient,
+                BufferId::Line {
+                    transparent: false,
+                    blend_mode: BlendMode::AlphaBlend,
+                },
+            );
+            self.draw_buffer(
+                rp,
+                render_data,
+                PipelineId::Point {
+                    blend_mode: BlendMode::AlphaBlend,
+                },
+                BufferId::Point {
+                    transparent: false,
+                    blend_mode: BlendMode::AlphaBlend,
                 },
             );
         }
-        self.draw_buffer(
-            rp,
-            render_data,
-            PipelineId::Line,
-            BufferId::Line { transparent: false },
-        );
-        self.draw_buffer(
-            rp,
-            render_data,
-            PipelineId::Point,
-            BufferId::Point { transparent: false },
-        );
+        self.end_stage(rp, 1); // opaque
 
         // Execute F3D commands which have depth test enabled.
+        self.begin_stage(rp, 2); // depth_f3d
         self.f3d_renderer
             .render_command_range(rp, render_data.f3d_depth_cmd_range.clone());
+        self.end_stage(rp, 2);
 
-        // Draw transparent points and lines with depth test and write enabled.
-        self.draw_buffer(
-            rp,
-            render_data,
-            PipelineId::Line,
-            BufferId::Line { transparent: true },
-        );
-        self.draw_buffer(
-            rp,
-            render_data,
-            PipelineId::Point,
-            BufferId::Point { transparent: true },
-        );
-
-        // Render wall hitboxes before other transparent triangles.
-        // When two wall hitboxes overlap, we should not increase the opacity
-        // within their region of overlap (preference).
-        // The first pass writes only to the depth buffer to ensure that only
-        // the closest hitbox triangles are drawn, then the second pass draws
-        // them.
-        for color_write in [false, true] {
-            for surface_gradient in [false, true] {
+        // Draw transparent points and lines, wall hitboxes, then remaining transparent
+        // triangles. When bundled (see record_transparent_bundle) these are replayed as a
+        // single group, so per-stage timing/statistics are only available when profiling
+        // forces the bundle off.
+        if let Some((_, transparent_bundle)) = &render_data.bundles {
+            rp.execute_bundles([transparent_bundle]);
+        } else {
+            // Draw transparent points and lines with depth test and write enabled.
+            self.begin_stage(rp, 3); // transparent_lines_points
+            for blend_mode in BLEND_MODES {
                 self.draw_buffer(
                     rp,
                     render_data,
-                    PipelineId::Triangle {
-                        surface_gradient,
-                        depth_write: true,
-                        color_write,
+                    PipelineId::Line { blend_mode },
+                    BufferId::Line {
+                        transparent: true,
+                        blend_mode,
                     },
-                    BufferId::Triangle {
-                        transparency: TriangleTransparency::TransparentWallHitbox,
-                        surface_gradient,
+                );
+                self.draw_buffer(
+                    rp,
+                    render_data,
+                    PipelineId::Point { blend_mode },
+                    BufferId::Point {
+                        transparent: true,
+                        blend_mode,
                     },
                 );
             }
-        }
+            self.end_stage(rp, 3); // transparent_lines_points
 
-        // Render remaining transparent triangles.
-        // These will not be visible through wall hitboxes (which is fine
-        // because wall hitboxes are small), but wall hitboxes will be visible
-        // through them which is more important.
-        // These are rendered in the order they were added to the scene.
-        for surface_gradient in [false, true] {
-            self.draw_buffer(
-                rp,
-                render_data,
-                PipelineId::Triangle {
-                    surface_gradient,
-                    depth_write: false,
-                    color_write: true,
-                },
-                BufferId::Triangle {
-                    transparency: TriangleTransparency::Transparent,
-                    surface_gradient,
-                },
-            );
+            // Render wall hitboxes before other transparent triangles.
+            // When two wall hitboxes overlap, we should not increase the opacity
+            // within their region of overlap (preference).
+            // The first pass writes only to the depth buffer to ensure that only
+            // the closest hitbox triangles are drawn, then the second pass draws
+            // them.
+            self.begin_stage(rp, 4); // wall_hitboxes
+            for blend_mode in BLEND_MODES {
+                for color_write in [false, true] {
+                    for surface_gradient in [false, true] {
+                        self.draw_buffer(
+                            rp,
+                            render_data,
+                            PipelineId::Triangle {
+                                surface_gradient,
+                                depth_write: true,
+                                color_write,
+                                blend_mode,
+                            },
+                            BufferId::Triangle {
+                                transparency: TriangleTransparency::TransparentWallHitbox,
+                                surface_gradient,
+                                blend_mode,
+                            },
+                        );
+                    }
+                }
+            }
+            self.end_stage(rp, 4); // wall_hitboxes
+
+            // Render remaining transparent triangles.
+            // These will not be visible through wall hitboxes (which is fine
+            // because wall hitboxes are small), but wall hitboxes will be visible
+            // through them which is more important.
+            // These are rendered in the order they were added to the scene.
+            self.begin_stage(rp, 5); // transparent_triangles
+            for blend_mode in BLEND_MODES {
+                for surface_gradient in [false, true] {
+                    self.draw_buffer(
+                        rp,
+                        render_data,
+                        PipelineId::Triangle {
+                            surface_gradient,
+                            depth_write: false,
+                            color_write: true,
+                            blend_mode,
+                        },
+                        BufferId::Triangle {
+                            transparency: TriangleTransparency::Transparent,
+                            surface_gradient,
+                            blend_mode,
+                        },
+                    );
+                }
+            }
+            self.end_stage(rp, 5); // transparent_triangles
         }
 
         // Render post depth F3D commands (e.g. the HUD).
+        self.begin_stage(rp, 6); // post_depth_f3d
         self.f3d_renderer
             .render_command_range(rp, render_data.f3d_post_depth_cmd_range.clone());
+        self.end_stage(rp, 6); // post_depth_f3d
     }
 }